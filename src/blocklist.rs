@@ -0,0 +1,366 @@
+extern crate chrono;
+extern crate regex;
+
+use std::collections::{HashMap, VecDeque};
+use std::net::Ipv4Addr;
+use self::chrono::{DateTime, Duration, UTC};
+use self::regex::Regex;
+use elb_log_files::ELBLogEntry;
+
+/// Picks which `ELBLogEntry` values count as abusive hits against the
+/// sliding-window counter.
+#[derive(Clone)]
+pub enum StatusCodeFilter {
+    AtLeast(u16),
+    OneOf(Vec<u16>),
+}
+
+impl StatusCodeFilter {
+    fn matches(&self, code: u16) -> bool {
+        match *self {
+            StatusCodeFilter::AtLeast(min) => code >= min,
+            StatusCodeFilter::OneOf(ref codes) => codes.contains(&code),
+        }
+    }
+}
+
+/// Picks `OneOf` the explicit status codes when any were configured,
+/// otherwise falls back to the `AtLeast` threshold. Factored out of
+/// `BlocklistConfig::from_runtime_context` so the selection logic is
+/// testable without needing a real `RuntimeContext`.
+fn select_status_code_filter(status_codes: &Option<Vec<u16>>, min_status: Option<u16>) -> StatusCodeFilter {
+    match *status_codes {
+        Some(ref codes) if !codes.is_empty() => StatusCodeFilter::OneOf(codes.clone()),
+        _ => StatusCodeFilter::AtLeast(min_status.unwrap_or(400)),
+    }
+}
+
+pub struct BlocklistConfig {
+    pub window_secs: i64,
+    pub threshold: usize,
+    pub jail_secs: i64,
+    pub status_code_filter: StatusCodeFilter,
+    pub url_filter: Option<Regex>,
+}
+
+impl BlocklistConfig {
+    pub fn new(window_secs: i64, threshold: usize, jail_secs: i64, status_code_filter: StatusCodeFilter, url_filter: Option<Regex>) -> BlocklistConfig {
+        BlocklistConfig {
+            window_secs: window_secs,
+            threshold: threshold,
+            jail_secs: jail_secs,
+            status_code_filter: status_code_filter,
+            url_filter: url_filter,
+        }
+    }
+
+    /// Builds the config from CLI/env-sourced settings. Fails with the
+    /// `regex` crate's own error rather than panicking if the user supplies
+    /// an unparseable `blocklist_url_pattern`.
+    pub fn from_runtime_context(runtime_context: &::RuntimeContext) -> Result<BlocklistConfig, regex::Error> {
+        let status_code_filter = select_status_code_filter(
+            &runtime_context.blocklist_status_codes,
+            runtime_context.blocklist_min_status,
+        );
+
+        let url_filter = match runtime_context.blocklist_url_pattern {
+            Some(ref pattern) => Some(Regex::new(pattern)?),
+            None => None,
+        };
+
+        Ok(BlocklistConfig::new(
+            runtime_context.blocklist_window_secs.unwrap_or(600),
+            runtime_context.blocklist_threshold.unwrap_or(10),
+            runtime_context.blocklist_jail_secs.unwrap_or(3600),
+            status_code_filter,
+            url_filter,
+        ))
+    }
+}
+
+struct BannedHost {
+    expiry: DateTime<UTC>,
+}
+
+/// Tracks recent abusive hits per `client_address` using the timestamps
+/// embedded in the log itself, so replaying an archived log produces the
+/// same bans a live tail would have.
+pub struct Blocklist {
+    config: BlocklistConfig,
+    hits: HashMap<Ipv4Addr, VecDeque<DateTime<UTC>>>,
+    banned: HashMap<Ipv4Addr, BannedHost>,
+}
+
+impl Blocklist {
+    pub fn new(config: BlocklistConfig) -> Blocklist {
+        Blocklist {
+            config: config,
+            hits: HashMap::new(),
+            banned: HashMap::new(),
+        }
+    }
+
+    /// Feed a single log entry through the sliding-window counter, banning
+    /// the client address once it crosses the configured threshold within
+    /// the configured window.
+    pub fn observe(&mut self, entry: &ELBLogEntry) {
+        if !self.is_abusive(entry) {
+            return;
+        }
+
+        let ip = *entry.client_address.ip();
+        let timestamp = entry.timestamp;
+        let window_start = timestamp - Duration::seconds(self.config.window_secs);
+
+        let hits = self.hits.entry(ip).or_insert_with(VecDeque::new);
+        hits.push_back(timestamp);
+        // Entries for a single IP can arrive out of timestamp order once
+        // `process_files` fans multiple files out across worker threads onto
+        // one channel, so we can't assume `hits` stays sorted and trim only
+        // the front; retain is the only eviction that's correct either way.
+        hits.retain(|&t| t >= window_start);
+
+        if hits.len() >= self.config.threshold {
+            self.banned.insert(ip, BannedHost {
+                expiry: timestamp + Duration::seconds(self.config.jail_secs),
+            });
+        }
+    }
+
+    fn is_abusive(&self, entry: &ELBLogEntry) -> bool {
+        let status_match = self.config.status_code_filter.matches(entry.elb_status_code)
+            || self.config.status_code_filter.matches(entry.backend_status_code);
+        let url_match = self.config.url_filter.as_ref().map_or(false, |re| re.is_match(&entry.request_url));
+
+        status_match || url_match
+    }
+
+    /// Drop expired bans, measured against `now` rather than wall-clock time
+    /// so callers replaying historical logs can pass the log's own time.
+    pub fn expire(&mut self, now: DateTime<UTC>) {
+        self.banned.retain(|_, host| host.expiry > now);
+    }
+
+    pub fn banned_ips(&self) -> Vec<Ipv4Addr> {
+        let mut ips: Vec<_> = self.banned.keys().cloned().collect();
+        ips.sort();
+        ips
+    }
+
+    pub fn render(&self, format: &BanFormat) -> String {
+        match *format {
+            BanFormat::Cidr => self.banned_ips().iter()
+                .map(|ip| format!("{}/32", ip))
+                .collect::<Vec<_>>()
+                .join("\n"),
+
+            BanFormat::Ipset(ref set_name) => self.banned_ips().iter()
+                .map(|ip| format!("ipset add {} {}", set_name, ip))
+                .collect::<Vec<_>>()
+                .join("\n"),
+
+            BanFormat::Nftables(ref set_name) => format!(
+                "set {} {{\n    type ipv4_addr\n    elements = {{ {} }}\n}}",
+                set_name,
+                self.banned_ips().iter().map(|ip| ip.to_string()).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+pub enum BanFormat {
+    Cidr,
+    Ipset(String),
+    Nftables(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(client_ip: &str, timestamp: &str, elb_status_code: u16) -> ELBLogEntry {
+        ELBLogEntry {
+            timestamp: timestamp.parse().unwrap(),
+            elb_name: "elb-name".to_string(),
+            client_address: format!("{}:1234", client_ip).parse().unwrap(),
+            backend_address: "10.0.0.1:9000".parse().unwrap(),
+            request_processing_time: 0.0,
+            backend_processing_time: 0.0,
+            response_processing_time: 0.0,
+            elb_status_code: elb_status_code,
+            backend_status_code: 200,
+            received_bytes: 0,
+            sent_bytes: 0,
+            request_method: "GET".to_string(),
+            request_url: "http://example.com/".to_string(),
+            request_http_version: "HTTP/1.1".to_string(),
+            user_agent: None,
+            ssl_cipher: None,
+            ssl_protocol: None,
+            target_group_arn: None,
+            trace_id: None,
+            domain_name: None,
+            chosen_cert_arn: None,
+            matched_rule_priority: None,
+            actions_executed: None,
+            redirect_url: None,
+        }
+    }
+
+    fn config(threshold: usize) -> BlocklistConfig {
+        BlocklistConfig::new(60, threshold, 3600, StatusCodeFilter::AtLeast(400), None)
+    }
+
+    #[test]
+    fn select_status_code_filter_prefers_one_of_when_codes_are_configured() {
+        let filter = select_status_code_filter(&Some(vec![401, 403, 404]), Some(400));
+
+        match filter {
+            StatusCodeFilter::OneOf(codes) => assert_eq!(codes, vec![401, 403, 404]),
+            StatusCodeFilter::AtLeast(_) => panic!("expected OneOf"),
+        }
+    }
+
+    #[test]
+    fn select_status_code_filter_falls_back_to_at_least_when_no_codes_are_configured() {
+        let filter = select_status_code_filter(&None, Some(400));
+
+        match filter {
+            StatusCodeFilter::AtLeast(min) => assert_eq!(min, 400),
+            StatusCodeFilter::OneOf(_) => panic!("expected AtLeast"),
+        }
+    }
+
+    #[test]
+    fn select_status_code_filter_falls_back_when_codes_is_empty() {
+        let filter = select_status_code_filter(&Some(vec![]), Some(400));
+
+        match filter {
+            StatusCodeFilter::AtLeast(min) => assert_eq!(min, 400),
+            StatusCodeFilter::OneOf(_) => panic!("expected AtLeast"),
+        }
+    }
+
+    #[test]
+    fn observe_bans_using_a_one_of_status_code_filter() {
+        let mut blocklist = Blocklist::new(BlocklistConfig::new(
+            60, 1, 3600, StatusCodeFilter::OneOf(vec![401, 403, 404]), None
+        ));
+
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:00Z", 404));
+
+        assert!(!blocklist.banned_ips().is_empty());
+    }
+
+    #[test]
+    fn observe_ignores_a_status_code_not_in_the_one_of_list() {
+        let mut blocklist = Blocklist::new(BlocklistConfig::new(
+            60, 1, 3600, StatusCodeFilter::OneOf(vec![401, 403, 404]), None
+        ));
+
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:00Z", 500));
+
+        assert!(blocklist.banned_ips().is_empty());
+    }
+
+    #[test]
+    fn observe_bans_a_client_once_it_crosses_the_threshold() {
+        let mut blocklist = Blocklist::new(config(3));
+
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:00Z", 500));
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:01Z", 500));
+        assert!(blocklist.banned_ips().is_empty());
+
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:02Z", 500));
+        assert_eq!(blocklist.banned_ips(), vec!["1.2.3.4".parse().unwrap()]);
+    }
+
+    #[test]
+    fn observe_ignores_hits_that_do_not_match_the_status_code_filter() {
+        let mut blocklist = Blocklist::new(config(1));
+
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:00Z", 200));
+
+        assert!(blocklist.banned_ips().is_empty());
+    }
+
+    #[test]
+    fn observe_evicts_hits_that_fall_outside_the_sliding_window() {
+        let mut blocklist = Blocklist::new(config(2));
+
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:00Z", 500));
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:02:00Z", 500));
+
+        assert!(blocklist.banned_ips().is_empty());
+    }
+
+    #[test]
+    fn observe_evicts_a_stale_hit_even_when_a_newer_hit_sits_at_the_front() {
+        // A worker pool interleaving multiple files can push a hit whose
+        // timestamp is older than one already in the deque, so the stale
+        // entry ends up behind a front entry that still looks fresh. A
+        // front-only eviction loop would stop immediately and never see it.
+        let mut blocklist = Blocklist::new(BlocklistConfig::new(100, 3, 3600, StatusCodeFilter::AtLeast(400), None));
+
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:05:00Z", 500)); // front, "fresh" at every later check
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:02:30Z", 500)); // arrives out of order, stale by call 3
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:04:20Z", 500)); // window_start = 00:02:40Z here
+
+        assert!(blocklist.banned_ips().is_empty());
+    }
+
+    #[test]
+    fn expire_drops_bans_past_their_expiry() {
+        let mut blocklist = Blocklist::new(config(1));
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:00Z", 500));
+        assert!(!blocklist.banned_ips().is_empty());
+
+        blocklist.expire("2020-01-01T02:00:00Z".parse().unwrap());
+
+        assert!(blocklist.banned_ips().is_empty());
+    }
+
+    #[test]
+    fn expire_keeps_bans_that_have_not_yet_expired() {
+        let mut blocklist = Blocklist::new(config(1));
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:00Z", 500));
+
+        blocklist.expire("2020-01-01T00:30:00Z".parse().unwrap());
+
+        assert!(!blocklist.banned_ips().is_empty());
+    }
+
+    fn banned_blocklist() -> Blocklist {
+        let mut blocklist = Blocklist::new(config(1));
+        blocklist.observe(&entry("1.2.3.4", "2020-01-01T00:00:00Z", 500));
+        blocklist.observe(&entry("5.6.7.8", "2020-01-01T00:00:00Z", 500));
+        blocklist
+    }
+
+    #[test]
+    fn render_cidr_lists_each_banned_ip_as_a_slash_32() {
+        let blocklist = banned_blocklist();
+
+        assert_eq!(blocklist.render(&BanFormat::Cidr), "1.2.3.4/32\n5.6.7.8/32")
+    }
+
+    #[test]
+    fn render_ipset_emits_an_add_command_per_banned_ip() {
+        let blocklist = banned_blocklist();
+
+        assert_eq!(
+            blocklist.render(&BanFormat::Ipset("blocked".to_string())),
+            "ipset add blocked 1.2.3.4\nipset add blocked 5.6.7.8"
+        )
+    }
+
+    #[test]
+    fn render_nftables_emits_a_single_set_with_every_banned_ip() {
+        let blocklist = banned_blocklist();
+
+        assert_eq!(
+            blocklist.render(&BanFormat::Nftables("blocked".to_string())),
+            "set blocked {\n    type ipv4_addr\n    elements = { 1.2.3.4, 5.6.7.8 }\n}"
+        )
+    }
+}
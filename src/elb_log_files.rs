@@ -1,31 +1,48 @@
 extern crate walkdir;
 extern crate chrono;
+extern crate threadpool;
 
 use std::path;
 use self::walkdir::{WalkDir, DirEntry, Error as WalkDirError};
 use std::fs::File;
+use std::io;
 use std::io::BufReader;
 use std::io::BufRead;
 use self::chrono::{DateTime, UTC};
 use std::error::Error;
 use std::str::FromStr;
 use std::net::SocketAddrV4;
+use std::sync::mpsc;
+use self::threadpool::ThreadPool;
 
 pub struct ELBLogEntry {
-    timestamp: DateTime<UTC>,
-    elb_name: String,
-    client_address: SocketAddrV4,
-    backend_address: SocketAddrV4,
-    request_processing_time: f32,
-    backend_processing_time: f32,
-    response_processing_time: f32,
-    elb_status_code: u16,
-    backend_status_code: u16,
-    received_bytes: u64,
-    sent_bytes: u64,
-    request_method: String,
-    request_url: String,
-    request_http_version: String
+    pub timestamp: DateTime<UTC>,
+    pub elb_name: String,
+    pub client_address: SocketAddrV4,
+    pub backend_address: SocketAddrV4,
+    pub request_processing_time: f32,
+    pub backend_processing_time: f32,
+    pub response_processing_time: f32,
+    pub elb_status_code: u16,
+    pub backend_status_code: u16,
+    pub received_bytes: u64,
+    pub sent_bytes: u64,
+    pub request_method: String,
+    pub request_url: String,
+    pub request_http_version: String,
+
+    // Only present on the extended ALB log format; `None` for classic ELB
+    // lines, which don't carry any of this.
+    pub user_agent: Option<String>,
+    pub ssl_cipher: Option<String>,
+    pub ssl_protocol: Option<String>,
+    pub target_group_arn: Option<String>,
+    pub trace_id: Option<String>,
+    pub domain_name: Option<String>,
+    pub chosen_cert_arn: Option<String>,
+    pub matched_rule_priority: Option<String>,
+    pub actions_executed: Option<String>,
+    pub redirect_url: Option<String>,
 }
 
 pub fn file_list(dir: &path::Path, filenames: &mut Vec<DirEntry>) -> Result<usize, WalkDirError> {
@@ -38,35 +55,46 @@ pub fn file_list(dir: &path::Path, filenames: &mut Vec<DirEntry>) -> Result<usiz
     Ok(filenames.len())
 }
 
-pub fn process_files(runtime_context: &::RuntimeContext, filenames: Vec<walkdir::DirEntry>) -> usize {
-    let debug = runtime_context.debug;
-    let mut record_count = 0;
-    for filename in filenames {
-        debug!(debug, "Processing file {}.", filename.path().display());
-        match File::open(filename.path()) {
-            Ok(file) => {
-                let buffered_file = BufReader::new(&file);
-                let recs: Vec<_> = buffered_file.lines()
-                    .map(|x| {
-                        parse_line(&(x.unwrap()))
-                    })
-                    .collect();
-                record_count += recs.len();
-                debug!(debug, "Found {} records in file {}.", recs.len(), filename.path().display());
-            },
-            Err(e) => {
-                println!("ERROR: {}", e);
-            }
-        }
+/// A source of raw log lines, abstracting over where the bytes actually come
+/// from. `process_files` only depends on this trait, so a local directory
+/// walk and a remote fetch (see `remote_log_source`) are interchangeable.
+pub trait LogSource {
+    /// A human-readable identifier used in debug output (a path or URL).
+    fn name(&self) -> String;
+
+    /// Stream the source's lines one at a time.
+    fn lines(&self) -> Box<Iterator<Item = io::Result<String>>>;
+}
+
+/// The original, local-directory flavour of `LogSource`, backed by a single
+/// `DirEntry` discovered by `file_list`.
+pub struct LocalFileSource {
+    path: path::PathBuf,
+}
+
+impl LocalFileSource {
+    pub fn new(entry: &DirEntry) -> LocalFileSource {
+        LocalFileSource { path: entry.path().to_path_buf() }
+    }
+}
+
+impl LogSource for LocalFileSource {
+    fn name(&self) -> String {
+        self.path.display().to_string()
     }
 
-    record_count
+    fn lines(&self) -> Box<Iterator<Item = io::Result<String>>> {
+        match File::open(&self.path) {
+            Ok(file) => Box::new(BufReader::new(file).lines()),
+            Err(e) => Box::new(vec![Err(e)].into_iter()),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct ParsingError{
     property: &'static str,
-    inner_description: Box<Error>,
+    inner_description: Box<Error + Send>,
 }
 
 #[derive(Debug)]
@@ -75,6 +103,72 @@ pub struct ParsingErrors {
     errors: Vec<ParsingError>,
 }
 
+pub type ParseResult = Result<Box<ELBLogEntry>, Box<ParsingErrors>>;
+
+/// Hands each `source` off to a fixed worker pool, one file per job; workers
+/// push every parsed record or `ParsingErrors` onto a shared channel as soon
+/// as its line is parsed, so nothing larger than one line is ever held in
+/// memory and a slow file no longer blocks the ones behind it. The returned
+/// receiver doubles as an iterator, so a caller can fold `record_count`/error
+/// counts incrementally as results arrive (see `aggregate`).
+///
+/// Takes `debug`/`worker_threads` directly rather than the whole
+/// `RuntimeContext` so it (and its worker-pool/channel behavior) can be
+/// exercised in tests without needing one.
+pub fn process_files<S>(debug: bool, worker_threads: Option<usize>, sources: Vec<S>) -> mpsc::Receiver<ParseResult>
+    where S: LogSource + Send + 'static,
+{
+    let pool = ThreadPool::new(worker_threads.unwrap_or(4));
+    let (sender, receiver) = mpsc::channel();
+
+    for source in sources {
+        let sender = sender.clone();
+        pool.execute(move || {
+            debug!(debug, "Processing file {}.", source.name());
+            for line in source.lines() {
+                let result = match line {
+                    Ok(text) => parse_line(&text),
+                    Err(e) => Err(Box::new(ParsingErrors {
+                        record: String::new(),
+                        errors: vec![ParsingError {
+                            property: "line",
+                            inner_description: Box::new(e),
+                        }],
+                    })),
+                };
+
+                if sender.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    receiver
+}
+
+#[derive(Default)]
+pub struct ProcessingSummary {
+    pub record_count: usize,
+    pub error_count: usize,
+}
+
+/// The single aggregator side of the `process_files` channel: folds whatever
+/// the worker pool produces into running totals as it arrives.
+pub fn aggregate<I: Iterator<Item = ParseResult>>(results: I) -> ProcessingSummary {
+    let mut summary = ProcessingSummary::default();
+    for result in results {
+        match result {
+            Ok(_) => summary.record_count += 1,
+            Err(e) => {
+                println!("ERROR: {:?}", e);
+                summary.error_count += 1;
+            }
+        }
+    }
+    summary
+}
+
 const TIMESTAMP: &'static str = "timestamp";
 const CLIENT_ADDRESS: &'static str = "client address";
 const BACKEND_ADDRESS: &'static str = "backend address";
@@ -85,27 +179,116 @@ const ELB_STATUS_CODE: &'static str = "ELB status code";
 const BE_STATUS_CODE: &'static str = "backend status code";
 const RECEIVED_BYTES: &'static str = "received bytes";
 const SENT_BYTES: &'static str = "sent bytes";
+const REQUEST_LINE: &'static str = "request line";
+
+// Classic ELB access logs have a fixed 12-field layout once the quoted
+// request line collapses to a single token. The extended ALB format adds a
+// leading `type` field and a run of extra quoted/unquoted fields after it;
+// `parse_line` tells the two apart by how many tokens the line tokenized
+// into, rather than by sniffing content.
+const CLASSIC_FIELD_COUNT: usize = 12;
+const ALB_FIELD_COUNT: usize = 24;
+
+/// Splits a log line into fields the same way the ELB/ALB log writer quotes
+/// them: a double-quoted segment (the request line, the user agent, ...) is
+/// one token even though it contains spaces, unlike a bare `split(' ')`.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ' ' if !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+#[derive(Debug)]
+struct MalformedRequestLine(String);
+
+impl ::std::fmt::Display for MalformedRequestLine {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "expected \"METHOD URL VERSION\", got {:?}", self.0)
+    }
+}
+
+impl Error for MalformedRequestLine {
+    fn description(&self) -> &str {
+        "malformed request line"
+    }
+}
+
+/// Splits the quoted `"METHOD URL VERSION"` token `tokenize` already pulled
+/// out of the line; reports a single `ParsingError` if it has fewer than
+/// three space-separated parts. Anything after the second space is kept,
+/// spaces included, as the version.
+fn parse_request_line(token: &str, errors: &mut Vec<ParsingError>) -> Option<(String, String, String)> {
+    let parts: Vec<_> = token.splitn(3, ' ').collect();
+    if parts.len() == 3 {
+        Some((parts[0].to_string(), parts[1].to_string(), parts[2].to_string()))
+    } else {
+        errors.push(ParsingError {
+            property: REQUEST_LINE,
+            inner_description: Box::new(MalformedRequestLine(token.to_string())),
+        });
+        None
+    }
+}
 
 pub fn parse_line(line: &String) -> Result<Box<ELBLogEntry>, Box<ParsingErrors>> {
-    let split_line: Vec<_> = line.split(" ").collect();
+    let tokens = tokenize(line);
+
+    match tokens.len() {
+        CLASSIC_FIELD_COUNT => parse_classic_line(line, &tokens),
+        ALB_FIELD_COUNT => parse_alb_line(line, &tokens),
+        n => Err(Box::new(ParsingErrors {
+            record: line.clone(),
+            errors: vec![ParsingError {
+                property: "field count",
+                inner_description: Box::new(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected {} or {} fields, got {}", CLASSIC_FIELD_COUNT, ALB_FIELD_COUNT, n),
+                )),
+            }],
+        })),
+    }
+}
+
+fn parse_classic_line(line: &String, tokens: &[String]) -> Result<Box<ELBLogEntry>, Box<ParsingErrors>> {
     let mut errors: Vec<ParsingError> = Vec::new();
 
-    let ts = parse_property::<DateTime<UTC>>(split_line[0], TIMESTAMP, &mut errors);
-    let clnt_addr = parse_property::<SocketAddrV4>(split_line[2], CLIENT_ADDRESS, &mut errors);
-    let be_addr = parse_property::<SocketAddrV4>(split_line[3], BACKEND_ADDRESS, &mut errors);
-    let req_proc_time = parse_property::<f32>(split_line[4], REQUEST_PROCESSING_TIME, &mut errors);
-    let be_proc_time = parse_property::<f32>(split_line[5], BACKEND_PROCESSING_TIME, &mut errors);
-    let res_proc_time = parse_property::<f32>(split_line[6], RESPONSE_PROCESSING_TIME, &mut errors);
-    let elb_sc = parse_property::<u16>(split_line[7], ELB_STATUS_CODE, &mut errors);
-    let be_sc = parse_property::<u16>(split_line[8], BE_STATUS_CODE, &mut errors);
-    let bytes_received = parse_property::<u64>(split_line[9], RECEIVED_BYTES, &mut errors);
-    let bytes_sent = parse_property::<u64>(split_line[10], SENT_BYTES, &mut errors);
+    let ts = parse_property::<DateTime<UTC>>(&tokens[0], TIMESTAMP, &mut errors);
+    let clnt_addr = parse_property::<SocketAddrV4>(&tokens[2], CLIENT_ADDRESS, &mut errors);
+    let be_addr = parse_property::<SocketAddrV4>(&tokens[3], BACKEND_ADDRESS, &mut errors);
+    let req_proc_time = parse_property::<f32>(&tokens[4], REQUEST_PROCESSING_TIME, &mut errors);
+    let be_proc_time = parse_property::<f32>(&tokens[5], BACKEND_PROCESSING_TIME, &mut errors);
+    let res_proc_time = parse_property::<f32>(&tokens[6], RESPONSE_PROCESSING_TIME, &mut errors);
+    let elb_sc = parse_property::<u16>(&tokens[7], ELB_STATUS_CODE, &mut errors);
+    let be_sc = parse_property::<u16>(&tokens[8], BE_STATUS_CODE, &mut errors);
+    let bytes_received = parse_property::<u64>(&tokens[9], RECEIVED_BYTES, &mut errors);
+    let bytes_sent = parse_property::<u64>(&tokens[10], SENT_BYTES, &mut errors);
+    let request_line = parse_request_line(&tokens[11], &mut errors);
 
     if errors.is_empty() {
+        let (method, url, version) = request_line.unwrap();
         Ok(Box::new(
             ELBLogEntry {
                 timestamp: ts.unwrap(),
-                elb_name: split_line[1].to_string(),
+                elb_name: tokens[1].clone(),
                 client_address: clnt_addr.unwrap(),
                 backend_address: be_addr.unwrap(),
                 request_processing_time: req_proc_time.unwrap(),
@@ -115,9 +298,74 @@ pub fn parse_line(line: &String) -> Result<Box<ELBLogEntry>, Box<ParsingErrors>>
                 backend_status_code: be_sc.unwrap(),
                 received_bytes: bytes_received.unwrap(),
                 sent_bytes: bytes_sent.unwrap(),
-                request_method: split_line[11].trim_matches('"').to_string(),
-                request_url: split_line[12].to_string(),
-                request_http_version: split_line[13].trim_matches('"').to_string()
+                request_method: method,
+                request_url: url,
+                request_http_version: version,
+                user_agent: None,
+                ssl_cipher: None,
+                ssl_protocol: None,
+                target_group_arn: None,
+                trace_id: None,
+                domain_name: None,
+                chosen_cert_arn: None,
+                matched_rule_priority: None,
+                actions_executed: None,
+                redirect_url: None,
+            }
+        ))
+    } else {
+        Err(Box::new(
+            ParsingErrors {
+                record: line.clone(),
+                errors: errors
+            }
+        ))
+    }
+}
+
+fn parse_alb_line(line: &String, tokens: &[String]) -> Result<Box<ELBLogEntry>, Box<ParsingErrors>> {
+    let mut errors: Vec<ParsingError> = Vec::new();
+
+    let ts = parse_property::<DateTime<UTC>>(&tokens[1], TIMESTAMP, &mut errors);
+    let clnt_addr = parse_property::<SocketAddrV4>(&tokens[3], CLIENT_ADDRESS, &mut errors);
+    let be_addr = parse_property::<SocketAddrV4>(&tokens[4], BACKEND_ADDRESS, &mut errors);
+    let req_proc_time = parse_property::<f32>(&tokens[5], REQUEST_PROCESSING_TIME, &mut errors);
+    let be_proc_time = parse_property::<f32>(&tokens[6], BACKEND_PROCESSING_TIME, &mut errors);
+    let res_proc_time = parse_property::<f32>(&tokens[7], RESPONSE_PROCESSING_TIME, &mut errors);
+    let elb_sc = parse_property::<u16>(&tokens[8], ELB_STATUS_CODE, &mut errors);
+    let be_sc = parse_property::<u16>(&tokens[9], BE_STATUS_CODE, &mut errors);
+    let bytes_received = parse_property::<u64>(&tokens[10], RECEIVED_BYTES, &mut errors);
+    let bytes_sent = parse_property::<u64>(&tokens[11], SENT_BYTES, &mut errors);
+    let request_line = parse_request_line(&tokens[12], &mut errors);
+
+    if errors.is_empty() {
+        let (method, url, version) = request_line.unwrap();
+        Ok(Box::new(
+            ELBLogEntry {
+                timestamp: ts.unwrap(),
+                elb_name: tokens[2].clone(),
+                client_address: clnt_addr.unwrap(),
+                backend_address: be_addr.unwrap(),
+                request_processing_time: req_proc_time.unwrap(),
+                backend_processing_time: be_proc_time.unwrap(),
+                response_processing_time: res_proc_time.unwrap(),
+                elb_status_code: elb_sc.unwrap(),
+                backend_status_code: be_sc.unwrap(),
+                received_bytes: bytes_received.unwrap(),
+                sent_bytes: bytes_sent.unwrap(),
+                request_method: method,
+                request_url: url,
+                request_http_version: version,
+                user_agent: Some(tokens[13].clone()),
+                ssl_cipher: Some(tokens[14].clone()),
+                ssl_protocol: Some(tokens[15].clone()),
+                target_group_arn: Some(tokens[16].clone()),
+                trace_id: Some(tokens[17].clone()),
+                domain_name: Some(tokens[18].clone()),
+                chosen_cert_arn: Some(tokens[19].clone()),
+                matched_rule_priority: Some(tokens[20].clone()),
+                actions_executed: Some(tokens[22].clone()),
+                redirect_url: Some(tokens[23].clone()),
             }
         ))
     } else {
@@ -132,7 +380,7 @@ pub fn parse_line(line: &String) -> Result<Box<ELBLogEntry>, Box<ParsingErrors>>
 
 fn parse_property<T>(raw_prop: &str, prop_name: &'static str, errors: &mut Vec<ParsingError>) -> Option<T>
     where T: FromStr,
-    T::Err: Error + 'static,
+    T::Err: Error + Send + 'static,
 {
     match raw_prop.parse::<T>() {
         Ok(parsed) => Some(parsed),
@@ -255,4 +503,151 @@ mod tests {
 
 		assert_eq!(elb_log_entry.elb_name, "elb-name")
 	}
+
+    #[test]
+	fn parse_line_leaves_the_alb_only_fields_unset_for_a_classic_log_entry() {
+        let elb_log_entry = parse_line(&TEST_LINE.to_string()).unwrap();
+
+		assert_eq!(elb_log_entry.user_agent, None);
+		assert_eq!(elb_log_entry.ssl_cipher, None);
+		assert_eq!(elb_log_entry.ssl_protocol, None);
+		assert_eq!(elb_log_entry.target_group_arn, None);
+		assert_eq!(elb_log_entry.trace_id, None);
+		assert_eq!(elb_log_entry.domain_name, None);
+		assert_eq!(elb_log_entry.chosen_cert_arn, None);
+		assert_eq!(elb_log_entry.matched_rule_priority, None);
+		assert_eq!(elb_log_entry.actions_executed, None);
+		assert_eq!(elb_log_entry.redirect_url, None)
+	}
+
+    const ALB_TEST_LINE: &'static str = "http 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 \
+    192.168.1.1:2817 10.0.0.1:80 0.001 0.002 0.000 200 200 34 366 \
+    \"GET http://www.example.com:80/ HTTP/1.1\" \"curl/7.46.0\" - - \
+    arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 \
+    \"Root=1-58337281-1d84f3d73c47ec4e58577259\" \"www.example.com\" \"arn:aws:acm:us-east-2:123456789012:certificate/12345678-1234-1234-1234-123456789012\" \
+    1 2018-07-02T22:22:48.364000Z \"forward\" \"-\" \
+    ";
+
+    #[test]
+	fn parse_line_returns_an_alb_log_entry_with_the_request_url() {
+        let elb_log_entry = parse_line(&ALB_TEST_LINE.to_string()).unwrap();
+
+		assert_eq!(elb_log_entry.request_url, "http://www.example.com:80/")
+	}
+
+    #[test]
+	fn parse_line_returns_an_alb_log_entry_with_the_user_agent() {
+        let elb_log_entry = parse_line(&ALB_TEST_LINE.to_string()).unwrap();
+
+		assert_eq!(elb_log_entry.user_agent, Some("curl/7.46.0".to_string()))
+	}
+
+    #[test]
+	fn parse_line_returns_an_alb_log_entry_with_the_target_group_arn() {
+        let elb_log_entry = parse_line(&ALB_TEST_LINE.to_string()).unwrap();
+
+		assert_eq!(elb_log_entry.target_group_arn, Some("arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067".to_string()))
+	}
+
+    #[test]
+	fn parse_line_returns_an_alb_log_entry_with_the_actions_executed() {
+        let elb_log_entry = parse_line(&ALB_TEST_LINE.to_string()).unwrap();
+
+		assert_eq!(elb_log_entry.actions_executed, Some("forward".to_string()))
+	}
+
+    #[test]
+	fn parse_line_reports_a_malformed_request_line() {
+        let bad_line = "2015-08-15T23:43:05.302180Z elb-name 172.16.1.6:54814 \
+        172.16.1.5:9000 0.000039 0.145507 0.00003 200 200 0 7582 \
+        \"not-a-request-line\"".to_string();
+
+        assert!(parse_line(&bad_line).is_err())
+	}
+}
+
+#[cfg(test)]
+mod process_files_tests {
+    use super::{process_files, aggregate, LogSource, ParseResult};
+    use std::cell::RefCell;
+    use std::io;
+
+    const GOOD_LINE: &'static str = "2015-08-15T23:43:05.302180Z elb-name 172.16.1.6:54814 \
+    172.16.1.5:9000 0.000039 0.145507 0.00003 200 200 0 7582 \
+    \"GET http://some.domain.com:80/path0/path1?param0=p0&param1=p1 HTTP/1.1\" \
+    ";
+
+    /// A `LogSource` backed by a fixed, in-memory line list, so tests can
+    /// drive `process_files`' worker pool/channel plumbing without touching
+    /// the filesystem. `lines()` can only be called once per instance, same
+    /// as a real file handle.
+    struct FakeSource {
+        source_name: String,
+        lines: RefCell<Option<Vec<io::Result<String>>>>,
+    }
+
+    impl FakeSource {
+        fn new(name: &str, lines: Vec<io::Result<String>>) -> FakeSource {
+            FakeSource {
+                source_name: name.to_string(),
+                lines: RefCell::new(Some(lines)),
+            }
+        }
+    }
+
+    impl LogSource for FakeSource {
+        fn name(&self) -> String {
+            self.source_name.clone()
+        }
+
+        fn lines(&self) -> Box<Iterator<Item = io::Result<String>>> {
+            let lines = self.lines.borrow_mut().take().unwrap_or_default();
+            Box::new(lines.into_iter())
+        }
+    }
+
+    fn collect_results<S: LogSource + Send + 'static>(sources: Vec<S>) -> Vec<ParseResult> {
+        process_files(false, Some(1), sources).into_iter().collect()
+    }
+
+    #[test]
+    fn process_files_counts_a_bad_read_midway_through_a_file_as_an_error_without_panicking() {
+        let source = FakeSource::new("bad-read.log", vec![
+            Ok(GOOD_LINE.to_string()),
+            Err(io::Error::new(io::ErrorKind::Other, "disk read failed")),
+            Ok(GOOD_LINE.to_string()),
+        ]);
+
+        let summary = aggregate(collect_results(vec![source]).into_iter());
+
+        assert_eq!(summary.record_count, 2);
+        assert_eq!(summary.error_count, 1);
+    }
+
+    #[test]
+    fn process_files_aggregates_results_from_multiple_sources() {
+        let sources = vec![
+            FakeSource::new("one.log", vec![Ok(GOOD_LINE.to_string()), Ok("not a log line".to_string())]),
+            FakeSource::new("two.log", vec![Ok(GOOD_LINE.to_string()), Ok(GOOD_LINE.to_string())]),
+        ];
+
+        let summary = aggregate(collect_results(sources).into_iter());
+
+        assert_eq!(summary.record_count, 3);
+        assert_eq!(summary.error_count, 1);
+    }
+
+    #[test]
+    fn process_files_receiver_terminates_once_every_worker_finishes() {
+        let sources = vec![
+            FakeSource::new("one.log", vec![Ok(GOOD_LINE.to_string())]),
+            FakeSource::new("two.log", vec![Ok(GOOD_LINE.to_string()), Ok(GOOD_LINE.to_string())]),
+        ];
+
+        // If a sender were ever leaked, this iteration would block forever
+        // instead of the test completing.
+        let results = collect_results(sources);
+
+        assert_eq!(results.len(), 3);
+    }
 }
@@ -0,0 +1,326 @@
+//! Live dashboard surface for a long-running `process_files` pass, gated
+//! behind the `metrics-server` feature so the default build doesn't pull in
+//! an HTTP framework it doesn't need.
+#![cfg(feature = "metrics-server")]
+
+extern crate actix_web;
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use self::actix_web::{server, App, HttpResponse, State};
+use elb_log_files::ELBLogEntry;
+
+#[derive(Default, Clone)]
+pub struct ClientStats {
+    pub request_count: u64,
+    pub bytes_sent: u64,
+}
+
+// Width of one `LatencyHistogram` bucket. Percentiles are reported as the
+// lower edge of the bucket a sample falls in rather than exactly (a 0.099s
+// sample reads as 0.095s), trading precision for a bounded,
+// O(buckets) footprint instead of keeping every sample ever seen.
+const LATENCY_BUCKET_MS: u32 = 5;
+
+/// A fixed-footprint stand-in for the raw sample `Vec` `Stats` used to keep:
+/// counts are bucketed by backend processing time instead of appending every
+/// sample, so a multi-gigabyte run holds at most one entry per bucket rather
+/// than one per request.
+#[derive(Default)]
+pub struct LatencyHistogram {
+    buckets: BTreeMap<u32, u64>,
+    count: u64,
+    // ELB/ALB logs emit -1 for processing times on several failure paths
+    // (backend closed the connection, 504s, ...); those aren't a latency at
+    // all, so they're counted separately instead of saturating into bucket 0
+    // and dragging percentiles down.
+    negative_count: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, seconds: f32) {
+        if seconds < 0.0 {
+            self.negative_count += 1;
+            return;
+        }
+
+        let bucket = (seconds * 1000.0) as u32 / LATENCY_BUCKET_MS;
+        *self.buckets.entry(bucket).or_insert(0) += 1;
+        self.count += 1;
+    }
+
+    pub fn negative_count(&self) -> u64 {
+        self.negative_count
+    }
+
+    /// Resolves every requested percentile (e.g. `&[0.50, 0.95, 0.99]`) in a
+    /// single walk over the (already sorted, by `BTreeMap`) bucket counts,
+    /// rather than sorting and indexing the full sample set once per
+    /// percentile. `pcts` must be in ascending order.
+    pub fn percentiles(&self, pcts: &[f64]) -> Vec<f32> {
+        if self.count == 0 {
+            return pcts.iter().map(|_| 0.0).collect();
+        }
+
+        let targets: Vec<u64> = pcts.iter()
+            .map(|pct| ((self.count - 1) as f64 * pct).round() as u64)
+            .collect();
+
+        let mut results = vec![0.0f32; pcts.len()];
+        let mut cumulative = 0u64;
+        let mut next = 0usize;
+        for (&bucket, &count) in &self.buckets {
+            cumulative += count;
+            while next < targets.len() && cumulative > targets[next] {
+                results[next] = (bucket * LATENCY_BUCKET_MS) as f32 / 1000.0;
+                next += 1;
+            }
+            if next >= targets.len() {
+                break;
+            }
+        }
+        results
+    }
+}
+
+/// The aggregated view of every `ELBLogEntry` seen so far. Updated in place
+/// as records flow out of `process_files`, so a request against `/metrics`
+/// always reflects progress on an in-flight run.
+#[derive(Default)]
+pub struct Stats {
+    pub request_count: u64,
+    pub status_codes: HashMap<u16, u64>,
+    pub backend_processing_times: LatencyHistogram,
+    pub request_urls: HashMap<String, u64>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub clients: HashMap<Ipv4Addr, ClientStats>,
+}
+
+impl Stats {
+    pub fn record(&mut self, entry: &ELBLogEntry) {
+        self.request_count += 1;
+        *self.status_codes.entry(entry.elb_status_code).or_insert(0) += 1;
+        self.backend_processing_times.record(entry.backend_processing_time);
+        *self.request_urls.entry(entry.request_url.clone()).or_insert(0) += 1;
+        self.bytes_sent += entry.sent_bytes;
+        self.bytes_received += entry.received_bytes;
+
+        let client = self.clients.entry(*entry.client_address.ip()).or_insert_with(ClientStats::default);
+        client.request_count += 1;
+        client.bytes_sent += entry.sent_bytes;
+    }
+
+    /// p50/p95/p99 backend processing time, computed in one pass so a
+    /// `/metrics` scrape doesn't walk the histogram three times while
+    /// holding the lock other threads need for `record`.
+    pub fn backend_processing_percentiles(&self) -> (f32, f32, f32) {
+        let values = self.backend_processing_times.percentiles(&[0.50, 0.95, 0.99]);
+        (values[0], values[1], values[2])
+    }
+
+    /// Requests where the backend reported no real processing time (ELB/ALB
+    /// logs `-1` on a closed connection, a 504, ...), excluded from the
+    /// percentiles above rather than folded into them.
+    pub fn backend_processing_negative_count(&self) -> u64 {
+        self.backend_processing_times.negative_count()
+    }
+
+    pub fn top_urls(&self, n: usize) -> Vec<(&String, &u64)> {
+        let mut urls: Vec<_> = self.request_urls.iter().collect();
+        urls.sort_by(|a, b| b.1.cmp(a.1));
+        urls.truncate(n);
+        urls
+    }
+}
+
+pub type SharedStats = Arc<Mutex<Stats>>;
+
+pub fn new_shared_stats() -> SharedStats {
+    Arc::new(Mutex::new(Stats::default()))
+}
+
+fn metrics_json(state: State<SharedStats>) -> HttpResponse {
+    let stats = state.lock().unwrap();
+
+    let status_codes = stats.status_codes.iter()
+        .map(|(code, count)| format!("\"{}\":{}", code, count))
+        .collect::<Vec<_>>()
+        .join(",");
+    let top_urls = stats.top_urls(10).iter()
+        .map(|&(url, count)| format!("{{\"url\":{:?},\"count\":{}}}", url, count))
+        .collect::<Vec<_>>()
+        .join(",");
+    let (p50, p95, p99) = stats.backend_processing_percentiles();
+
+    let body = format!(
+        "{{\"request_count\":{},\"status_codes\":{{{}}},\"backend_processing_time\":{{\"p50\":{},\"p95\":{},\"p99\":{},\"negative_samples\":{}}},\"top_urls\":[{}],\"bytes_sent\":{},\"bytes_received\":{},\"client_count\":{}}}",
+        stats.request_count,
+        status_codes,
+        p50, p95, p99, stats.backend_processing_negative_count(),
+        top_urls,
+        stats.bytes_sent,
+        stats.bytes_received,
+        stats.clients.len()
+    );
+
+    HttpResponse::Ok().content_type("application/json").body(body)
+}
+
+fn summary_text(state: State<SharedStats>) -> HttpResponse {
+    let stats = state.lock().unwrap();
+    let (p50, p95, p99) = stats.backend_processing_percentiles();
+    let body = format!(
+        "requests: {}\nbytes sent: {}\nbytes received: {}\nbackend processing time p50/p95/p99: {:.3}/{:.3}/{:.3}\nbackend processing time negative samples: {}\nclients seen: {}\n",
+        stats.request_count,
+        stats.bytes_sent,
+        stats.bytes_received,
+        p50, p95, p99,
+        stats.backend_processing_negative_count(),
+        stats.clients.len()
+    );
+
+    HttpResponse::Ok().content_type("text/plain").body(body)
+}
+
+/// Blocks the calling thread serving the dashboard; run it on its own
+/// thread alongside `process_files` so the two can share `stats`.
+pub fn serve(bind_addr: &str, stats: SharedStats) {
+    server::new(move || {
+        App::with_state(stats.clone())
+            .resource("/metrics", |r| r.f(metrics_json))
+            .resource("/metrics/summary", |r| r.f(summary_text))
+    })
+    .bind(bind_addr)
+    .expect("failed to bind metrics server")
+    .run();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(client_ip: &str, status_code: u16, backend_processing_time: f32, sent_bytes: u64) -> ELBLogEntry {
+        ELBLogEntry {
+            timestamp: "2020-01-01T00:00:00Z".parse().unwrap(),
+            elb_name: "elb-name".to_string(),
+            client_address: format!("{}:1234", client_ip).parse().unwrap(),
+            backend_address: "10.0.0.1:9000".parse().unwrap(),
+            request_processing_time: 0.0,
+            backend_processing_time: backend_processing_time,
+            response_processing_time: 0.0,
+            elb_status_code: status_code,
+            backend_status_code: status_code,
+            received_bytes: 0,
+            sent_bytes: sent_bytes,
+            request_method: "GET".to_string(),
+            request_url: "http://example.com/".to_string(),
+            request_http_version: "HTTP/1.1".to_string(),
+            user_agent: None,
+            ssl_cipher: None,
+            ssl_protocol: None,
+            target_group_arn: None,
+            trace_id: None,
+            domain_name: None,
+            chosen_cert_arn: None,
+            matched_rule_priority: None,
+            actions_executed: None,
+            redirect_url: None,
+        }
+    }
+
+    #[test]
+    fn record_increments_the_request_count() {
+        let mut stats = Stats::default();
+
+        stats.record(&entry("1.2.3.4", 200, 0.1, 100));
+
+        assert_eq!(stats.request_count, 1)
+    }
+
+    #[test]
+    fn record_tallies_status_codes() {
+        let mut stats = Stats::default();
+
+        stats.record(&entry("1.2.3.4", 200, 0.1, 100));
+        stats.record(&entry("1.2.3.4", 200, 0.1, 100));
+        stats.record(&entry("1.2.3.4", 500, 0.1, 100));
+
+        assert_eq!(stats.status_codes.get(&200), Some(&2))
+    }
+
+    #[test]
+    fn record_tracks_bytes_sent_per_client() {
+        let mut stats = Stats::default();
+
+        stats.record(&entry("1.2.3.4", 200, 0.1, 100));
+        stats.record(&entry("1.2.3.4", 200, 0.1, 50));
+
+        let client = stats.clients.get(&"1.2.3.4".parse().unwrap()).unwrap();
+        assert_eq!(client.bytes_sent, 150)
+    }
+
+    #[test]
+    fn backend_processing_percentiles_is_zero_with_no_samples() {
+        let stats = Stats::default();
+
+        assert_eq!(stats.backend_processing_percentiles(), (0.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn backend_processing_percentiles_reflects_recorded_samples() {
+        let mut stats = Stats::default();
+        for i in 0..100 {
+            stats.record(&entry("1.2.3.4", 200, i as f32 / 1000.0, 1));
+        }
+
+        let (p50, _, _) = stats.backend_processing_percentiles();
+
+        assert_eq!(p50, 0.05)
+    }
+
+    #[test]
+    fn top_urls_orders_by_descending_count_and_truncates() {
+        let mut stats = Stats::default();
+        *stats.request_urls.entry("/a".to_string()).or_insert(0) = 1;
+        *stats.request_urls.entry("/b".to_string()).or_insert(0) = 5;
+        *stats.request_urls.entry("/c".to_string()).or_insert(0) = 3;
+
+        let top = stats.top_urls(2);
+
+        assert_eq!(top, vec![(&"/b".to_string(), &5), (&"/c".to_string(), &3)])
+    }
+
+    #[test]
+    fn histogram_record_counts_negative_samples_separately_from_the_buckets() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(-1.0);
+        histogram.record(0.01);
+
+        assert_eq!(histogram.negative_count(), 1);
+        assert_eq!(histogram.percentiles(&[0.50]), vec![0.01]);
+    }
+
+    #[test]
+    fn record_excludes_negative_backend_processing_time_from_percentiles() {
+        let mut stats = Stats::default();
+        stats.record(&entry("1.2.3.4", 504, -1.0, 0));
+        stats.record(&entry("1.2.3.4", 200, 0.02, 1));
+
+        assert_eq!(stats.backend_processing_negative_count(), 1);
+        assert_eq!(stats.backend_processing_percentiles(), (0.02, 0.02, 0.02));
+    }
+
+    #[test]
+    fn histogram_percentiles_resolves_multiple_percentiles_in_one_pass() {
+        let mut histogram = LatencyHistogram::default();
+        for i in 1..=100 {
+            histogram.record(i as f32 / 1000.0);
+        }
+
+        let values = histogram.percentiles(&[0.50, 0.95, 0.99]);
+
+        assert_eq!(values, vec![0.05, 0.095, 0.095])
+    }
+}
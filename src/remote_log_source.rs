@@ -0,0 +1,591 @@
+extern crate curl;
+extern crate flate2;
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, BufRead, BufReader};
+use std::sync::mpsc;
+use std::thread;
+use self::curl::easy::{Easy, List};
+use self::flate2::read::GzDecoder;
+use elb_log_files::LogSource;
+use self::sigv4::Credentials;
+
+/// AWS Signature Version 4 request signing, self-contained so a GET against
+/// a private bucket is authenticated the same way whether it comes from
+/// `list_s3_objects` or an `S3LogSource` fetch. Scoped to exactly what S3
+/// needs (virtual-hosted-style GET, no body) rather than a general client.
+mod sigv4 {
+    extern crate chrono;
+    extern crate hmac;
+    extern crate sha2;
+
+    use std::env;
+    use self::chrono::UTC;
+    use self::hmac::{Hmac, Mac};
+    use self::sha2::{Digest, Sha256};
+
+    type HmacSha256 = Hmac<Sha256>;
+
+    /// The SHA-256 hex digest of an empty body, sent as `x-amz-content-sha256`
+    /// on every request here since ELB log fetches are always bodiless GETs.
+    pub const EMPTY_PAYLOAD_HASH: &'static str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    /// Credentials pulled from the environment, matching the variables the
+    /// AWS CLI/SDKs already read. Absent credentials mean "sign nothing" so
+    /// a pre-signed URL (or a public bucket) keeps working unsigned.
+    pub struct Credentials {
+        pub access_key: String,
+        pub secret_key: String,
+        pub session_token: Option<String>,
+        pub region: String,
+    }
+
+    impl Credentials {
+        pub fn from_env() -> Option<Credentials> {
+            let access_key = env::var("AWS_ACCESS_KEY_ID").ok()?;
+            let secret_key = env::var("AWS_SECRET_ACCESS_KEY").ok()?;
+            Some(Credentials {
+                access_key: access_key,
+                secret_key: secret_key,
+                session_token: env::var("AWS_SESSION_TOKEN").ok(),
+                region: env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            })
+        }
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.input(data);
+        hasher.result().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+        let mut mac = HmacSha256::new_varkey(key).expect("HMAC accepts a key of any length");
+        mac.input(data.as_bytes());
+        mac.result().code().to_vec()
+    }
+
+    /// Percent-encodes a single canonical-query-string component per SigV4's
+    /// rules, which (unlike plain RFC 3986 query encoding) also escapes `/`.
+    fn sigv4_encode(value: &str) -> String {
+        let mut encoded = String::with_capacity(value.len());
+        for byte in value.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    encoded.push(byte as char);
+                }
+                _ => encoded.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        encoded
+    }
+
+    /// Builds the canonical query string SigV4 signs over: parameters sorted
+    /// by (encoded) key, each key/value percent-encoded independently.
+    fn canonical_query_string(query_pairs: &[(&str, &str)]) -> String {
+        let mut pairs: Vec<String> = query_pairs.iter()
+            .map(|&(k, v)| format!("{}={}", sigv4_encode(k), sigv4_encode(v)))
+            .collect();
+        pairs.sort();
+        pairs.join("&")
+    }
+
+    /// Signs a GET request against `host`/`path` with `query_pairs`, returning
+    /// the headers to attach: `Host`, `x-amz-date`, `x-amz-content-sha256`,
+    /// the optional session-token header, and `Authorization`.
+    pub fn sign_s3_get(credentials: &Credentials, host: &str, path: &str, query_pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        let now = UTC::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date_stamp = now.format("%Y%m%d").to_string();
+        let credential_scope = format!("{}/{}/s3/aws4_request", date_stamp, credentials.region);
+
+        let canonical_query = canonical_query_string(query_pairs);
+        let mut canonical_headers = format!("host:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n", host, EMPTY_PAYLOAD_HASH, amz_date);
+        let mut signed_headers = "host;x-amz-content-sha256;x-amz-date".to_string();
+        if let Some(ref token) = credentials.session_token {
+            canonical_headers = format!("{}x-amz-security-token:{}\n", canonical_headers, token);
+            signed_headers = format!("{};x-amz-security-token", signed_headers);
+        }
+
+        let canonical_request = format!(
+            "GET\n{}\n{}\n{}\n{}\n{}",
+            path, canonical_query, canonical_headers, signed_headers, EMPTY_PAYLOAD_HASH
+        );
+
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date, credential_scope, sha256_hex(canonical_request.as_bytes())
+        );
+
+        let k_date = hmac_sha256(format!("AWS4{}", credentials.secret_key).as_bytes(), &date_stamp);
+        let k_region = hmac_sha256(&k_date, &credentials.region);
+        let k_service = hmac_sha256(&k_region, "s3");
+        let k_signing = hmac_sha256(&k_service, "aws4_request");
+        let signature: String = hmac_sha256(&k_signing, &string_to_sign).iter().map(|b| format!("{:02x}", b)).collect();
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key, credential_scope, signed_headers, signature
+        );
+
+        let mut headers = vec![
+            ("Host".to_string(), host.to_string()),
+            ("x-amz-date".to_string(), amz_date),
+            ("x-amz-content-sha256".to_string(), EMPTY_PAYLOAD_HASH.to_string()),
+            ("Authorization".to_string(), authorization),
+        ];
+        if let Some(ref token) = credentials.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        headers
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn canonical_query_string_sorts_and_encodes_parameters() {
+            assert_eq!(
+                canonical_query_string(&[("prefix", "logs/2020"), ("list-type", "2")]),
+                "list-type=2&prefix=logs%2F2020"
+            )
+        }
+
+        #[test]
+        fn canonical_query_string_is_empty_with_no_parameters() {
+            assert_eq!(canonical_query_string(&[]), "")
+        }
+    }
+}
+
+fn curl_err_to_io(err: curl::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+/// Either transport failed (handled by `curl`) or S3 answered with a
+/// well-formed `<Error>` response (a mangled/expired continuation token,
+/// an access-denied bucket policy, ...), which `transfer.perform()` alone
+/// can't see since it only fails on transport errors, not HTTP status.
+#[derive(Debug)]
+pub enum S3Error {
+    Transport(curl::Error),
+    Api(String),
+}
+
+impl fmt::Display for S3Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            S3Error::Transport(ref e) => write!(f, "{}", e),
+            S3Error::Api(ref message) => write!(f, "S3 returned an error: {}", message),
+        }
+    }
+}
+
+impl Error for S3Error {
+    fn description(&self) -> &str {
+        match *self {
+            S3Error::Transport(ref e) => e.description(),
+            S3Error::Api(_) => "S3 API error",
+        }
+    }
+}
+
+impl From<curl::Error> for S3Error {
+    fn from(e: curl::Error) -> S3Error {
+        S3Error::Transport(e)
+    }
+}
+
+/// Reads the body of an HTTP(S) response as it arrives, without buffering
+/// the whole thing in memory: a background thread drives the `curl` easy
+/// handle and forwards each chunk `write_function` hands it over a channel.
+struct ChannelReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl ChannelReader {
+    /// `headers` carries any `Authorization`/SigV4 headers the caller signed
+    /// in advance; `fetch` itself doesn't know or care whether the URL is
+    /// signed, public, or pre-signed.
+    fn fetch(url: String, headers: Vec<(String, String)>) -> ChannelReader {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut handle = Easy::new();
+            if let Err(e) = handle.url(&url) {
+                let _ = sender.send(Err(curl_err_to_io(e)));
+                return;
+            }
+            if let Err(e) = handle.follow_location(true) {
+                let _ = sender.send(Err(curl_err_to_io(e)));
+                return;
+            }
+
+            let mut header_list = List::new();
+            for &(ref name, ref value) in &headers {
+                if let Err(e) = header_list.append(&format!("{}: {}", name, value)) {
+                    let _ = sender.send(Err(curl_err_to_io(e)));
+                    return;
+                }
+            }
+            if let Err(e) = handle.http_headers(header_list) {
+                let _ = sender.send(Err(curl_err_to_io(e)));
+                return;
+            }
+
+            let result = {
+                let mut transfer = handle.transfer();
+                let chunk_sender = sender.clone();
+                let write_result = transfer.write_function(move |data| {
+                    let _ = chunk_sender.send(Ok(data.to_vec()));
+                    Ok(data.len())
+                });
+                match write_result {
+                    Ok(()) => transfer.perform(),
+                    Err(e) => Err(e),
+                }
+            };
+
+            if let Err(e) = result {
+                let _ = sender.send(Err(curl_err_to_io(e)));
+            }
+        });
+
+        ChannelReader {
+            receiver: receiver,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.buffer.len() {
+            match self.receiver.recv() {
+                Ok(Ok(chunk)) => {
+                    self.buffer = chunk;
+                    self.position = 0;
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let available = &self.buffer[self.position..];
+        let n = ::std::cmp::min(available.len(), out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// A gzipped ELB log fetched over HTTP(S), streamed straight into
+/// `parse_line` without ever touching disk.
+pub struct HttpLogSource {
+    url: String,
+}
+
+impl HttpLogSource {
+    pub fn new(url: &str) -> HttpLogSource {
+        HttpLogSource { url: url.to_string() }
+    }
+}
+
+/// Fetches `url` and decompresses it as a gzip stream, shared by
+/// `HttpLogSource` and `S3LogSource` so only the headers they sign differ.
+fn gzip_lines(url: String, headers: Vec<(String, String)>) -> Box<Iterator<Item = io::Result<String>>> {
+    let body = ChannelReader::fetch(url, headers);
+    match GzDecoder::new(body) {
+        Ok(decoder) => Box::new(BufReader::new(decoder).lines()),
+        Err(e) => Box::new(vec![Err(e)].into_iter()),
+    }
+}
+
+impl LogSource for HttpLogSource {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    fn lines(&self) -> Box<Iterator<Item = io::Result<String>>> {
+        gzip_lines(self.url.clone(), Vec::new())
+    }
+}
+
+/// One gzipped object under an S3 bucket/prefix, addressed the same way ELB
+/// writes its date-partitioned access log keys.
+pub struct S3LogSource {
+    bucket: String,
+    key: String,
+}
+
+impl S3LogSource {
+    pub fn new(bucket: &str, key: &str) -> S3LogSource {
+        S3LogSource {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    fn host(&self) -> String {
+        format!("{}.s3.amazonaws.com", self.bucket)
+    }
+
+    fn path(&self) -> String {
+        format!("/{}", self.key)
+    }
+
+    fn url(&self) -> String {
+        format!("https://{}{}", self.host(), self.path())
+    }
+
+    /// Signs the object GET when AWS credentials are available in the
+    /// environment; falls back to no headers (an unsigned request, e.g.
+    /// against a public object or a pre-signed URL) when they aren't.
+    fn headers(&self) -> Vec<(String, String)> {
+        match Credentials::from_env() {
+            Some(credentials) => sigv4::sign_s3_get(&credentials, &self.host(), &self.path(), &[]),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl LogSource for S3LogSource {
+    fn name(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.key)
+    }
+
+    fn lines(&self) -> Box<Iterator<Item = io::Result<String>>> {
+        gzip_lines(self.url(), self.headers())
+    }
+}
+
+/// Percent-encodes a query parameter value per RFC 3986, since a `prefix`
+/// or continuation token containing `/`, `+`, spaces, etc. would otherwise
+/// either be rejected by S3 or silently resolve to the wrong object range.
+fn uri_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// List the object keys under an S3 prefix via the ListObjectsV2 REST API,
+/// so a date-partitioned bucket of ELB logs can be turned into one
+/// `S3LogSource` per key. A single response page is capped at 1000 keys, so
+/// this follows `NextContinuationToken` until `IsTruncated` says `false`.
+pub fn list_s3_objects(bucket: &str, prefix: &str) -> Result<Vec<String>, S3Error> {
+    let mut keys = Vec::new();
+    let mut continuation_token: Option<String> = None;
+    let host = format!("{}.s3.amazonaws.com", bucket);
+    let credentials = Credentials::from_env();
+
+    loop {
+        let mut query_pairs = vec![("list-type", "2"), ("prefix", prefix)];
+        if let Some(ref token) = continuation_token {
+            query_pairs.push(("continuation-token", token));
+        }
+        let query_string = query_pairs.iter()
+            .map(|&(k, v)| format!("{}={}", uri_encode(k), uri_encode(v)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let list_url = format!("https://{}/?{}", host, query_string);
+
+        let headers = match credentials {
+            Some(ref credentials) => sigv4::sign_s3_get(credentials, &host, "/", &query_pairs),
+            None => Vec::new(),
+        };
+
+        let mut body = Vec::new();
+        {
+            let mut handle = Easy::new();
+            handle.url(&list_url)?;
+            handle.follow_location(true)?;
+            let mut header_list = List::new();
+            for &(ref name, ref value) in &headers {
+                header_list.append(&format!("{}: {}", name, value))?;
+            }
+            handle.http_headers(header_list)?;
+            let mut transfer = handle.transfer();
+            transfer.write_function(|data| {
+                body.extend_from_slice(data);
+                Ok(data.len())
+            })?;
+            transfer.perform()?;
+        }
+
+        let xml = String::from_utf8_lossy(&body).into_owned();
+        if let Some(message) = extract_tag(&xml, "Message") {
+            if xml.contains("<Error>") {
+                return Err(S3Error::Api(message));
+            }
+        }
+        keys.extend(extract_keys(&xml));
+
+        continuation_token = if is_truncated(&xml) {
+            extract_tag(&xml, "NextContinuationToken")
+        } else {
+            None
+        };
+
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+
+    Ok(keys)
+}
+
+fn extract_tag(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)?;
+    let after_start = &xml[start + open.len()..];
+    let end = after_start.find(&close)?;
+    Some(after_start[..end].to_string())
+}
+
+fn is_truncated(xml: &str) -> bool {
+    extract_tag(xml, "IsTruncated").map_or(false, |v| v == "true")
+}
+
+fn extract_keys(xml: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<Key>") {
+        let after_start = &rest[start + "<Key>".len()..];
+        match after_start.find("</Key>") {
+            Some(end) => {
+                keys.push(after_start[..end].to_string());
+                rest = &after_start[end + "</Key>".len()..];
+            }
+            None => break,
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST_OBJECTS_XML: &'static str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+    <ListBucketResult><IsTruncated>true</IsTruncated>\
+    <Contents><Key>logs/2020/01/01/a.log.gz</Key></Contents>\
+    <Contents><Key>logs/2020/01/01/b.log.gz</Key></Contents>\
+    <NextContinuationToken>abc123</NextContinuationToken></ListBucketResult>";
+
+    #[test]
+    fn extract_keys_returns_every_key_element() {
+        assert_eq!(
+            extract_keys(LIST_OBJECTS_XML),
+            vec!["logs/2020/01/01/a.log.gz".to_string(), "logs/2020/01/01/b.log.gz".to_string()]
+        )
+    }
+
+    #[test]
+    fn extract_keys_returns_an_empty_vec_when_there_are_no_keys() {
+        assert!(extract_keys("<ListBucketResult></ListBucketResult>").is_empty())
+    }
+
+    #[test]
+    fn is_truncated_reads_the_is_truncated_element() {
+        assert!(is_truncated(LIST_OBJECTS_XML))
+    }
+
+    #[test]
+    fn is_truncated_is_false_for_a_final_page() {
+        assert!(!is_truncated("<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>"))
+    }
+
+    #[test]
+    fn extract_tag_reads_the_continuation_token() {
+        assert_eq!(extract_tag(LIST_OBJECTS_XML, "NextContinuationToken"), Some("abc123".to_string()))
+    }
+
+    #[test]
+    fn extract_tag_returns_none_when_the_tag_is_absent() {
+        assert_eq!(extract_tag(LIST_OBJECTS_XML, "NotPresent"), None)
+    }
+
+    #[test]
+    fn uri_encode_leaves_unreserved_characters_untouched() {
+        assert_eq!(uri_encode("logs-2020.01.01_a~b"), "logs-2020.01.01_a~b")
+    }
+
+    #[test]
+    fn uri_encode_percent_encodes_slashes_and_other_reserved_characters() {
+        assert_eq!(uri_encode("logs/2020/01/01 a+b"), "logs%2F2020%2F01%2F01%20a%2Bb")
+    }
+
+    fn reader_from(chunks: Vec<io::Result<Vec<u8>>>) -> ChannelReader {
+        let (sender, receiver) = mpsc::channel();
+        for chunk in chunks {
+            sender.send(chunk).unwrap();
+        }
+        ChannelReader {
+            receiver: receiver,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+
+    #[test]
+    fn read_returns_a_chunk_smaller_than_the_caller_s_buffer_in_one_call() {
+        let mut reader = reader_from(vec![Ok(b"hello".to_vec())]);
+        let mut out = [0u8; 16];
+
+        let n = reader.read(&mut out).unwrap();
+
+        assert_eq!(&out[..n], b"hello")
+    }
+
+    #[test]
+    fn read_splits_a_chunk_larger_than_the_caller_s_buffer_across_calls() {
+        let mut reader = reader_from(vec![Ok(b"hello world".to_vec())]);
+        let mut out = [0u8; 5];
+
+        let first = reader.read(&mut out).unwrap();
+        let second = reader.read(&mut out).unwrap();
+
+        assert_eq!(&out[..first], b"hello");
+        assert_eq!(&out[..second], b" worl");
+    }
+
+    #[test]
+    fn read_moves_on_to_the_next_chunk_once_the_current_one_is_exhausted() {
+        let mut reader = reader_from(vec![Ok(b"ab".to_vec()), Ok(b"cd".to_vec())]);
+        let mut out = [0u8; 2];
+
+        let first = reader.read(&mut out).unwrap();
+        let second = reader.read(&mut out).unwrap();
+
+        assert_eq!(&out[..first], b"ab");
+        assert_eq!(&out[..second], b"cd");
+    }
+
+    #[test]
+    fn read_returns_zero_once_the_sender_is_dropped() {
+        let mut reader = reader_from(vec![]);
+        let mut out = [0u8; 8];
+
+        assert_eq!(reader.read(&mut out).unwrap(), 0)
+    }
+
+    #[test]
+    fn read_propagates_an_error_sent_by_the_fetch_thread() {
+        let mut reader = reader_from(vec![Err(io::Error::new(io::ErrorKind::Other, "boom"))]);
+        let mut out = [0u8; 8];
+
+        assert!(reader.read(&mut out).is_err())
+    }
+}